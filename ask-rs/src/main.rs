@@ -1,8 +1,9 @@
 use clap::Parser;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::env;
 use std::fs;
-use std::io::{self, Read};
+use std::io::{self, BufRead, BufReader, Read, Write};
 
 #[derive(Parser, Debug)]
 #[command(name = "ask")]
@@ -13,8 +14,8 @@ struct Args {
     prompt: Vec<String>,
 
     /// Model to use
-    #[arg(short, long, default_value = "gpt-oss:latest")]
-    model: String,
+    #[arg(short, long)]
+    model: Option<String>,
 
     /// System prompt
     #[arg(short, long)]
@@ -32,9 +33,88 @@ struct Args {
     #[arg(long)]
     list_models: bool,
 
+    /// Stream tokens as they arrive (Ollama provider only; defaults to on when stdout is a TTY)
+    #[arg(long)]
+    stream: bool,
+
+    /// Disable streaming even on a TTY
+    #[arg(long)]
+    no_stream: bool,
+
     /// Show version
     #[arg(short = 'V', long)]
     version: bool,
+
+    /// Start an interactive multi-turn chat session
+    #[arg(long, alias = "repl")]
+    chat: bool,
+
+    /// Sampling temperature
+    #[arg(long)]
+    temperature: Option<f64>,
+
+    /// Nucleus sampling probability
+    #[arg(long = "top-p")]
+    top_p: Option<f64>,
+
+    /// Seed for deterministic sampling
+    #[arg(long)]
+    seed: Option<i64>,
+
+    /// Context window size in tokens
+    #[arg(long = "num-ctx", default_value_t = 4096)]
+    num_ctx: u32,
+
+    /// Maximum number of tokens to generate
+    #[arg(long = "num-predict")]
+    num_predict: Option<i32>,
+
+    /// Stop sequence (repeatable)
+    #[arg(long = "stop")]
+    stop: Vec<String>,
+
+    /// Backend to target
+    #[arg(long, value_enum)]
+    provider: Option<Provider>,
+
+    /// Load a named preset (system prompt, model, temperature) from the config file
+    #[arg(long)]
+    role: Option<String>,
+
+    /// Save the current system prompt, model, and temperature as a named preset
+    #[arg(long = "save-role")]
+    save_role: Option<String>,
+
+    /// Cache responses on disk, keyed by a hash of the request (only when --seed is set or
+    /// --temperature is 0 or below, and the reply is not streamed live to the terminal)
+    #[arg(long)]
+    cache: bool,
+
+    /// Disable the on-disk cache for this run
+    #[arg(long = "no-cache")]
+    no_cache: bool,
+
+    /// Clear the on-disk response cache and exit
+    #[arg(long = "clear-cache")]
+    clear_cache: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, clap::ValueEnum)]
+enum Provider {
+    Ollama,
+    Openai,
+}
+
+impl Provider {
+    fn resolve(cli: Option<Provider>) -> Provider {
+        if let Some(p) = cli {
+            return p;
+        }
+        match env::var("ASK_PROVIDER").ok().as_deref() {
+            Some("openai") => Provider::Openai,
+            _ => Provider::Ollama,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -44,6 +124,35 @@ struct ChatRequest {
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<Options>,
+}
+
+#[derive(Serialize, Default, Clone)]
+struct Options {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "top_p")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_ctx: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<i32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+}
+
+impl Options {
+    fn is_empty(&self) -> bool {
+        self.temperature.is_none()
+            && self.top_p.is_none()
+            && self.seed.is_none()
+            && self.num_ctx.is_none()
+            && self.num_predict.is_none()
+            && self.stop.is_empty()
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -52,11 +161,66 @@ struct Message {
     content: String,
 }
 
+#[derive(Serialize, Deserialize, Default, Clone)]
+struct Role {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Config {
+    #[serde(default)]
+    roles: std::collections::HashMap<String, Role>,
+}
+
 #[derive(Deserialize)]
 struct ChatResponse {
     message: Message,
 }
 
+#[derive(Deserialize)]
+struct ChatChunk {
+    message: Message,
+    done: bool,
+    #[serde(default)]
+    total_duration: Option<u64>,
+    #[serde(default)]
+    eval_count: Option<u64>,
+    #[serde(default)]
+    eval_duration: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct OpenAiRequest {
+    model: String,
+    messages: Vec<Message>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "top_p")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "max_tokens")]
+    max_tokens: Option<i32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiResponse {
+    choices: Vec<OpenAiChoice>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiChoice {
+    message: Message,
+}
+
 #[derive(Deserialize)]
 struct ModelsResponse {
     models: Vec<ModelInfo>,
@@ -76,9 +240,181 @@ fn get_default_model() -> String {
     env::var("ASK_MODEL").unwrap_or_else(|_| "gpt-oss:latest".to_string())
 }
 
+fn get_host(provider: Provider) -> String {
+    match provider {
+        Provider::Ollama => get_ollama_host(),
+        Provider::Openai => {
+            env::var("OPENAI_BASE_URL").unwrap_or_else(|_| "https://api.openai.com".to_string())
+        }
+    }
+}
+
+fn get_api_key() -> Option<String> {
+    env::var("OPENAI_API_KEY")
+        .or_else(|_| env::var("ASK_API_KEY"))
+        .ok()
+}
+
+fn config_path() -> std::path::PathBuf {
+    if let Ok(dir) = env::var("ASK_CONFIG_DIR") {
+        return std::path::PathBuf::from(dir).join("config.toml");
+    }
+    #[cfg(unix)]
+    let home = env::var("HOME").ok();
+    #[cfg(windows)]
+    let home = env::var("USERPROFILE").ok();
+    #[cfg(not(any(unix, windows)))]
+    let home: Option<String> = None;
+
+    std::path::PathBuf::from(home.unwrap_or_else(|| ".".to_string()))
+        .join(".config")
+        .join("ask")
+        .join("config.toml")
+}
+
+fn load_config() -> Config {
+    fs::read_to_string(config_path())
+        .ok()
+        .and_then(|s| toml::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_config(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, toml::to_string_pretty(config)?)?;
+    Ok(())
+}
+
+fn apply_role(
+    role: &Role,
+    model_explicit: bool,
+    system: &mut Option<String>,
+    model: &mut String,
+    temperature: &mut Option<f64>,
+) {
+    if system.is_none() {
+        *system = role.system.clone();
+    }
+    if let Some(role_model) = &role.model
+        && !model_explicit
+    {
+        *model = role_model.clone();
+    }
+    if temperature.is_none() {
+        *temperature = role.temperature;
+    }
+}
+
+fn get_cache_dir() -> std::path::PathBuf {
+    if let Ok(dir) = env::var("ASK_CACHE_DIR") {
+        return std::path::PathBuf::from(dir);
+    }
+    #[cfg(unix)]
+    let home = env::var("HOME").ok();
+    #[cfg(windows)]
+    let home = env::var("USERPROFILE").ok();
+    #[cfg(not(any(unix, windows)))]
+    let home: Option<String> = None;
+
+    std::path::PathBuf::from(home.unwrap_or_else(|| ".".to_string()))
+        .join(".cache")
+        .join("ask")
+}
+
+fn cache_key(
+    provider: Provider,
+    host: &str,
+    model: &str,
+    messages: &[Message],
+    options: &Options,
+    format: Option<&str>,
+) -> String {
+    let payload = serde_json::json!({
+        "provider": provider,
+        "host": host,
+        "model": model,
+        "messages": messages,
+        "options": options,
+        "format": format,
+    });
+    let mut hasher = Sha256::new();
+    hasher.update(payload.to_string().as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+fn cache_read(dir: &std::path::Path, key: &str) -> Option<String> {
+    fs::read_to_string(dir.join(key)).ok()
+}
+
+fn cache_write(dir: &std::path::Path, key: &str, content: &str) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(key), content)?;
+    Ok(())
+}
+
+fn clear_cache(dir: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+enum AskError {
+    NotReady(String),
+    Http(String),
+}
+
+impl std::fmt::Display for AskError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AskError::NotReady(msg) => write!(f, "{}", msg),
+            AskError::Http(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for AskError {}
+
+fn friendly_request_error(err: ureq::Error, provider: Provider, host: &str) -> AskError {
+    match err {
+        ureq::Error::Transport(_) => match provider {
+            Provider::Ollama => AskError::NotReady(format!(
+                "Ollama doesn't appear to be running at {} — start it with `ollama serve`",
+                host
+            )),
+            Provider::Openai => AskError::NotReady(format!(
+                "Couldn't reach the OpenAI-compatible backend at {} — check OPENAI_BASE_URL and your network connection",
+                host
+            )),
+        },
+        ureq::Error::Status(404, _) if provider == Provider::Ollama => AskError::Http(
+            "Model not found (HTTP 404). Run `ask --list-models` to see what's available."
+                .to_string(),
+        ),
+        ureq::Error::Status(401, _) if provider == Provider::Openai => AskError::Http(
+            "Authentication failed (HTTP 401). Check OPENAI_API_KEY or ASK_API_KEY.".to_string(),
+        ),
+        ureq::Error::Status(code, response) => {
+            let body = response.into_string().unwrap_or_default();
+            let backend = match provider {
+                Provider::Ollama => "Ollama",
+                Provider::Openai => "The OpenAI-compatible backend",
+            };
+            AskError::Http(format!("{} returned HTTP {}: {}", backend, code, body))
+        }
+    }
+}
+
 fn list_models(host: &str) -> Result<(), Box<dyn std::error::Error>> {
     let url = format!("{}/api/tags", host);
-    let resp: ModelsResponse = ureq::get(&url).call()?.into_json()?;
+    let resp: ModelsResponse = ureq::get(&url)
+        .call()
+        .map_err(|e| friendly_request_error(e, Provider::Ollama, host))?
+        .into_json()?;
     
     println!("Available models:\n");
     let mut models = resp.models;
@@ -91,15 +427,23 @@ fn list_models(host: &str) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[derive(Clone)]
+struct RequestConfig {
+    provider: Provider,
+    host: String,
+    model: String,
+    json_mode: bool,
+    stream: bool,
+    print_live: bool,
+    options: Options,
+    cache: bool,
+}
+
 fn ask(
-    host: &str,
-    model: &str,
+    cfg: &RequestConfig,
     prompt: &str,
     system: Option<&str>,
-    json_mode: bool,
 ) -> Result<String, Box<dyn std::error::Error>> {
-    let url = format!("{}/api/chat", host);
-    
     let mut messages = Vec::new();
     if let Some(sys) = system {
         messages.push(Message {
@@ -112,19 +456,243 @@ fn ask(
         content: prompt.to_string(),
     });
 
-    let request = ChatRequest {
-        model: model.to_string(),
+    if cfg.cache {
+        let format = if cfg.json_mode { Some("json") } else { None };
+        let key = cache_key(cfg.provider, &cfg.host, &cfg.model, &messages, &cfg.options, format);
+        let dir = get_cache_dir();
+        if let Some(cached) = cache_read(&dir, &key) {
+            return Ok(cached);
+        }
+        let response = ask_with_messages(cfg, messages)?;
+        let _ = cache_write(&dir, &key, &response);
+        return Ok(response);
+    }
+
+    ask_with_messages(cfg, messages)
+}
+
+fn ask_with_messages(
+    cfg: &RequestConfig,
+    messages: Vec<Message>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    match cfg.provider {
+        Provider::Ollama => ask_ollama(cfg, messages),
+        Provider::Openai => ask_openai(cfg, messages),
+    }
+}
+
+fn ask_openai(
+    cfg: &RequestConfig,
+    messages: Vec<Message>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let url = format!("{}/v1/chat/completions", cfg.host);
+
+    let request = OpenAiRequest {
+        model: cfg.model.clone(),
         messages,
         stream: false,
-        format: if json_mode { Some("json".to_string()) } else { None },
+        temperature: cfg.options.temperature,
+        top_p: cfg.options.top_p,
+        seed: cfg.options.seed,
+        max_tokens: cfg.options.num_predict,
+        stop: cfg.options.stop.clone(),
     };
 
-    let resp: ChatResponse = ureq::post(&url)
-        .set("Content-Type", "application/json")
-        .send_json(&request)?
+    let mut req = ureq::post(&url).set("Content-Type", "application/json");
+    if let Some(key) = get_api_key() {
+        req = req.set("Authorization", &format!("Bearer {}", key));
+    }
+
+    let resp: OpenAiResponse = req
+        .send_json(&request)
+        .map_err(|e| friendly_request_error(e, cfg.provider, &cfg.host))?
         .into_json()?;
+    let choice = resp
+        .choices
+        .into_iter()
+        .next()
+        .ok_or("OpenAI response contained no choices")?;
 
-    Ok(resp.message.content)
+    Ok(choice.message.content)
+}
+
+fn ask_ollama(
+    cfg: &RequestConfig,
+    messages: Vec<Message>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let url = format!("{}/api/chat", cfg.host);
+
+    let request = ChatRequest {
+        model: cfg.model.clone(),
+        messages,
+        stream: cfg.stream,
+        format: if cfg.json_mode { Some("json".to_string()) } else { None },
+        options: if cfg.options.is_empty() {
+            None
+        } else {
+            Some(cfg.options.clone())
+        },
+    };
+
+    let resp = ureq::post(&url)
+        .set("Content-Type", "application/json")
+        .send_json(&request)
+        .map_err(|e| friendly_request_error(e, cfg.provider, &cfg.host))?;
+
+    if !cfg.stream {
+        let resp: ChatResponse = resp.into_json()?;
+        return Ok(resp.message.content);
+    }
+
+    let reader = BufReader::new(resp.into_reader());
+    let mut full = String::new();
+    let stdout = io::stdout();
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let chunk: ChatChunk = serde_json::from_str(&line)?;
+        full.push_str(&chunk.message.content);
+        if cfg.print_live {
+            let mut handle = stdout.lock();
+            write!(handle, "{}", chunk.message.content)?;
+            handle.flush()?;
+        }
+        if chunk.done
+            && let (Some(total_duration), Some(eval_count)) =
+                (chunk.total_duration, chunk.eval_count)
+        {
+            let secs = total_duration as f64 / 1_000_000_000.0;
+            let tok_per_sec = if let Some(eval_duration) = chunk.eval_duration {
+                eval_count as f64 / (eval_duration as f64 / 1_000_000_000.0)
+            } else {
+                0.0
+            };
+            eprintln!(
+                "\n[{} tokens in {:.2}s, {:.1} tok/s]",
+                eval_count, secs, tok_per_sec
+            );
+        }
+    }
+    if cfg.print_live {
+        println!();
+    }
+
+    Ok(full)
+}
+
+fn chat_loop(
+    mut cfg: RequestConfig,
+    system: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut messages: Vec<Message> = Vec::new();
+    if let Some(sys) = system {
+        messages.push(Message {
+            role: "system".to_string(),
+            content: sys,
+        });
+    }
+
+    println!(
+        "ask --chat ({}). Type /system, /save, /load, /reset, /model, /exit.",
+        cfg.model
+    );
+
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            break;
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("/system ") {
+            if let Some(existing) = messages.iter_mut().find(|m| m.role == "system") {
+                existing.content = rest.to_string();
+            } else {
+                messages.insert(
+                    0,
+                    Message {
+                        role: "system".to_string(),
+                        content: rest.to_string(),
+                    },
+                );
+            }
+            println!("System prompt set.");
+            continue;
+        }
+
+        if let Some(path) = line.strip_prefix("/save ") {
+            match serde_json::to_string_pretty(&messages).map_err(|e| e.to_string())
+                .and_then(|json| fs::write(path, json).map_err(|e| e.to_string()))
+            {
+                Ok(()) => println!("Saved conversation to {}", path),
+                Err(e) => eprintln!("Error saving to {}: {}", path, e),
+            }
+            continue;
+        }
+
+        if let Some(path) = line.strip_prefix("/load ") {
+            match fs::read_to_string(path).map_err(|e| e.to_string())
+                .and_then(|contents| serde_json::from_str(&contents).map_err(|e| e.to_string()))
+            {
+                Ok(loaded) => {
+                    messages = loaded;
+                    println!("Loaded conversation from {}", path);
+                }
+                Err(e) => eprintln!("Error loading {}: {}", path, e),
+            }
+            continue;
+        }
+
+        if line == "/reset" {
+            messages.clear();
+            println!("Conversation reset.");
+            continue;
+        }
+
+        if let Some(new_model) = line.strip_prefix("/model ") {
+            cfg.model = new_model.trim().to_string();
+            println!("Switched to model {}", cfg.model);
+            continue;
+        }
+
+        if line == "/exit" || line == "/quit" {
+            break;
+        }
+
+        messages.push(Message {
+            role: "user".to_string(),
+            content: line.to_string(),
+        });
+
+        match ask_with_messages(&cfg, messages.clone()) {
+            Ok(reply) => {
+                if !cfg.stream {
+                    println!("{}", reply);
+                }
+                messages.push(Message {
+                    role: "assistant".to_string(),
+                    content: reply,
+                });
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                messages.pop();
+            }
+        }
+    }
+
+    Ok(())
 }
 
 fn is_stdin_piped() -> bool {
@@ -147,6 +715,26 @@ fn is_stdin_piped() -> bool {
     }
 }
 
+fn is_stdout_tty() -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+        unsafe { libc::isatty(io::stdout().as_raw_fd()) != 0 }
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::System::Console::GetConsoleMode;
+        let handle = io::stdout().as_raw_handle();
+        let mut mode = 0;
+        unsafe { GetConsoleMode(handle as _, &mut mode) != 0 }
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        false
+    }
+}
+
 fn read_stdin() -> Option<String> {
     if !is_stdin_piped() {
         return None;
@@ -161,6 +749,15 @@ fn read_stdin() -> Option<String> {
     }
 }
 
+fn exit_with_error(e: Box<dyn std::error::Error>) -> ! {
+    if matches!(e.downcast_ref::<AskError>(), Some(AskError::NotReady(_))) {
+        eprintln!("{}", e);
+        std::process::exit(3);
+    }
+    eprintln!("Error: {}", e);
+    std::process::exit(1);
+}
+
 fn main() {
     let args = Args::parse();
     
@@ -171,18 +768,89 @@ fn main() {
         return;
     }
 
-    let host = get_ollama_host();
-    let model = if args.model == "gpt-oss:latest" {
-        get_default_model()
-    } else {
-        args.model
-    };
+    let provider = Provider::resolve(args.provider);
+    let host = get_host(provider);
+    let model_explicit = args.model.is_some();
+    let mut model = args.model.unwrap_or_else(get_default_model);
 
     if args.list_models {
-        if let Err(e) = list_models(&host) {
-            eprintln!("Error listing models: {}", e);
+        if provider != Provider::Ollama {
+            eprintln!("--list-models is only supported for the Ollama provider");
             std::process::exit(1);
         }
+        if let Err(e) = list_models(&host) {
+            exit_with_error(e);
+        }
+        return;
+    }
+
+    if args.clear_cache {
+        if let Err(e) = clear_cache(&get_cache_dir()) {
+            exit_with_error(e);
+        }
+        println!("Cleared cache at {}", get_cache_dir().display());
+        return;
+    }
+
+    let mut system = args.system;
+    let mut temperature = args.temperature;
+
+    if let Some(role_name) = &args.role {
+        let config = load_config();
+        match config.roles.get(role_name) {
+            Some(role) => apply_role(role, model_explicit, &mut system, &mut model, &mut temperature),
+            None => {
+                eprintln!(
+                    "Unknown role '{}'. Configure it in {} or create it with --save-role.",
+                    role_name,
+                    config_path().display()
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(role_name) = &args.save_role {
+        let mut config = load_config();
+        config.roles.insert(
+            role_name.clone(),
+            Role {
+                system: system.clone(),
+                model: Some(model.clone()),
+                temperature,
+            },
+        );
+        if let Err(e) = save_config(&config) {
+            exit_with_error(e);
+        }
+        println!("Saved role '{}' to {}", role_name, config_path().display());
+        return;
+    }
+
+    let options = Options {
+        temperature,
+        top_p: args.top_p,
+        seed: args.seed,
+        num_ctx: Some(args.num_ctx),
+        num_predict: args.num_predict,
+        stop: args.stop,
+    };
+
+    if args.chat {
+        let stream = provider == Provider::Ollama && !args.no_stream && (args.stream || is_stdout_tty());
+        let cfg = RequestConfig {
+            provider,
+            host,
+            model,
+            json_mode: false,
+            stream,
+            print_live: stream,
+            options,
+            cache: false,
+        };
+        if let Err(e) = chat_loop(cfg, system) {
+            exit_with_error(e);
+        }
         return;
     }
 
@@ -202,7 +870,24 @@ fn main() {
         std::process::exit(1);
     }
 
-    match ask(&host, &model, &prompt, args.system.as_deref(), args.json) {
+    let stream = provider == Provider::Ollama && !args.no_stream && (args.stream || is_stdout_tty());
+    let print_live = stream && !args.json && args.output.is_none();
+
+    let deterministic = options.seed.is_some() || options.temperature.is_some_and(|t| t <= 0.0);
+    let cache = args.cache && !args.no_cache && !print_live && deterministic;
+
+    let cfg = RequestConfig {
+        provider,
+        host,
+        model,
+        json_mode: args.json,
+        stream,
+        print_live,
+        options,
+        cache,
+    };
+
+    match ask(&cfg, &prompt, system.as_deref()) {
         Ok(response) => {
             if let Some(output_file) = args.output {
                 if let Err(e) = fs::write(&output_file, &response) {
@@ -210,13 +895,141 @@ fn main() {
                     std::process::exit(1);
                 }
                 println!("Written to {}", output_file);
-            } else {
+            } else if !print_live {
                 println!("{}", response);
             }
         }
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            std::process::exit(1);
-        }
+        Err(e) => exit_with_error(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_messages() -> Vec<Message> {
+        vec![Message {
+            role: "user".to_string(),
+            content: "hello".to_string(),
+        }]
+    }
+
+    #[test]
+    fn apply_role_fills_in_unset_fields() {
+        let role = Role {
+            system: Some("You are a pirate.".to_string()),
+            model: Some("llama3".to_string()),
+            temperature: Some(0.8),
+        };
+        let mut system = None;
+        let mut model = get_default_model();
+        let mut temperature = None;
+
+        apply_role(&role, false, &mut system, &mut model, &mut temperature);
+
+        assert_eq!(system.as_deref(), Some("You are a pirate."));
+        assert_eq!(model, "llama3");
+        assert_eq!(temperature, Some(0.8));
+    }
+
+    #[test]
+    fn apply_role_does_not_override_explicit_model() {
+        let role = Role {
+            system: None,
+            model: Some("llama3".to_string()),
+            temperature: None,
+        };
+        let mut system = None;
+        let mut model = "explicit-model".to_string();
+        let mut temperature = None;
+
+        apply_role(&role, true, &mut system, &mut model, &mut temperature);
+
+        assert_eq!(model, "explicit-model");
+    }
+
+    #[test]
+    fn apply_role_does_not_override_explicit_system_or_temperature() {
+        let role = Role {
+            system: Some("You are a pirate.".to_string()),
+            model: None,
+            temperature: Some(0.8),
+        };
+        let mut system = Some("You are a wizard.".to_string());
+        let mut model = get_default_model();
+        let mut temperature = Some(0.2);
+
+        apply_role(&role, false, &mut system, &mut model, &mut temperature);
+
+        assert_eq!(system.as_deref(), Some("You are a wizard."));
+        assert_eq!(temperature, Some(0.2));
+    }
+
+    #[test]
+    fn cache_key_is_stable_for_identical_requests() {
+        let messages = sample_messages();
+        let options = Options::default();
+        let a = cache_key(Provider::Ollama, "http://localhost:11434", "llama3", &messages, &options, None);
+        let b = cache_key(Provider::Ollama, "http://localhost:11434", "llama3", &messages, &options, None);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn cache_key_differs_by_provider() {
+        let messages = sample_messages();
+        let options = Options::default();
+        let ollama = cache_key(Provider::Ollama, "http://localhost:11434", "llama3", &messages, &options, None);
+        let openai = cache_key(Provider::Openai, "http://localhost:11434", "llama3", &messages, &options, None);
+        assert_ne!(
+            ollama, openai,
+            "same model name on different providers must not share a cache entry"
+        );
+    }
+
+    #[test]
+    fn cache_key_differs_by_host() {
+        let messages = sample_messages();
+        let options = Options::default();
+        let a = cache_key(Provider::Ollama, "http://localhost:11434", "llama3", &messages, &options, None);
+        let b = cache_key(Provider::Ollama, "http://other-host:11434", "llama3", &messages, &options, None);
+        assert_ne!(a, b, "same model name on different hosts must not share a cache entry");
+    }
+
+    #[test]
+    fn cache_key_differs_by_messages_and_options() {
+        let options = Options::default();
+        let base = cache_key(
+            Provider::Ollama,
+            "http://localhost:11434",
+            "llama3",
+            &sample_messages(),
+            &options,
+            None,
+        );
+        let other_messages = vec![Message {
+            role: "user".to_string(),
+            content: "goodbye".to_string(),
+        }];
+        let diff_messages = cache_key(
+            Provider::Ollama,
+            "http://localhost:11434",
+            "llama3",
+            &other_messages,
+            &options,
+            None,
+        );
+        assert_ne!(base, diff_messages);
+
+        let mut other_options = options.clone();
+        other_options.seed = Some(1);
+        let diff_options = cache_key(
+            Provider::Ollama,
+            "http://localhost:11434",
+            "llama3",
+            &sample_messages(),
+            &other_options,
+            None,
+        );
+        assert_ne!(base, diff_options);
     }
 }